@@ -12,88 +12,362 @@
 
 use std::{fmt, io};
 
-use yaml_rust::{yaml, Yaml, YamlEmitter};
+use yaml_rust::{yaml, Yaml};
 
 use serde::ser;
+use serde::ser::Error as SerError;
 
 use super::error::{Error, Result};
 
-pub struct Serializer;
+/// Controls how enum variants carrying a payload are represented in the
+/// generated YAML.
+///
+/// Unit variants are affected too: `Map` writes the bare variant name,
+/// `Tag` writes `!variant` with no payload, and `Untagged` writes `~`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EnumRepr {
+    /// `{variant: payload}`, a singleton map (the default, historical
+    /// behavior).
+    Map,
+    /// `!variant payload`, tagging the node with the variant name instead
+    /// of wrapping it in a map.
+    Tag,
+    /// The payload on its own, with the variant name dropped entirely.
+    Untagged,
+}
 
-impl ser::Serializer for Serializer {
-    type Ok = Yaml;
+impl Default for EnumRepr {
+    fn default() -> Self {
+        EnumRepr::Map
+    }
+}
+
+/// Controls how `serialize_bytes` represents a byte buffer.
+///
+/// This only affects types that call `Serializer::serialize_bytes`
+/// directly, such as `serde_bytes::Bytes`/`serde_bytes::ByteBuf`. A plain
+/// `Vec<u8>` or `&[u8]` serialized through serde's blanket `Serialize` impl
+/// for slices/`Vec` drives `serialize_seq`/`serialize_u8` per element
+/// instead, and is unaffected by this setting; wrap such a value in
+/// `serde_bytes::Bytes` (or use `#[serde(with = "serde_bytes")]`) to get
+/// `!!binary` treatment.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BytesRepr {
+    /// One `Yaml::Integer`/scalar per byte (the default, historical
+    /// behavior).
+    Array,
+    /// The standard YAML `!!binary` tag, with the bytes base64-encoded as
+    /// the scalar content.
+    Binary,
+}
+
+impl Default for BytesRepr {
+    fn default() -> Self {
+        BytesRepr::Array
+    }
+}
+
+const BASE64_CHARS: &'static [u8] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+fn base64_encode(data: &[u8]) -> String {
+    let mut out = String::with_capacity((data.len() + 2) / 3 * 4);
+    for chunk in data.chunks(3) {
+        let b0 = chunk[0];
+        let b1 = *chunk.get(1).unwrap_or(&0);
+        let b2 = *chunk.get(2).unwrap_or(&0);
+        out.push(BASE64_CHARS[(b0 >> 2) as usize] as char);
+        out.push(BASE64_CHARS[(((b0 & 0x03) << 4) | (b1 >> 4)) as usize] as char);
+        out.push(if chunk.len() > 1 {
+            BASE64_CHARS[(((b1 & 0x0f) << 2) | (b2 >> 6)) as usize] as char
+        } else {
+            '='
+        });
+        out.push(if chunk.len() > 2 {
+            BASE64_CHARS[(b2 & 0x3f) as usize] as char
+        } else {
+            '='
+        });
+    }
+    out
+}
+
+fn io_err(_: io::Error) -> Error {
+    Error::custom("failed to write YAML output")
+}
+
+/// Renders `v` as a YAML core-schema float scalar: `.nan`/`.inf`/`-.inf` for
+/// non-finite values, and otherwise `v.to_string()` with a trailing `.0`
+/// appended if Rust's rendering would otherwise round-trip as an integer.
+fn float_repr(v: f64) -> String {
+    if v.is_nan() {
+        ".nan".to_owned()
+    } else if v.is_infinite() {
+        if v > 0.0 { ".inf".to_owned() } else { "-.inf".to_owned() }
+    } else {
+        let repr = v.to_string();
+        if repr.contains('.') || repr.contains('e') || repr.contains('E') {
+            repr
+        } else {
+            repr + ".0"
+        }
+    }
+}
+
+/// Returns whether `s` needs to be quoted to round-trip as a YAML string
+/// scalar rather than being parsed as some other type or reserved word.
+///
+/// Ported from yaml_rust's own (private) `emitter::need_quotes`, so that
+/// this serializer and yaml_rust's `YamlEmitter` agree on what needs
+/// quoting: both reject any indicator/flow character anywhere in the
+/// string (not just `": "`/`" #"`), leading or trailing spaces, and the
+/// same set of control bytes, on top of the reserved words and
+/// number-like strings.
+fn need_quotes(s: &str) -> bool {
+    s.is_empty() || s.starts_with(' ') || s.ends_with(' ') ||
+    s.contains(|c: char| {
+        match c {
+            ':' | '{' | '}' | '[' | ']' | ',' | '&' | '*' | '#' | '?' | '|' | '-' | '<' | '>' |
+            '=' | '!' | '%' | '@' | '`' | '"' | '\'' | '\\' | '\0'..='\x06' | '\t' | '\n' |
+            '\r' | '\x0e'..='\x1f' => true,
+            _ => false,
+        }
+    }) ||
+    match &*s.to_lowercase() {
+        "~" | "null" | "true" | "false" => true,
+        _ => false,
+    } ||
+    s.parse::<f64>().is_ok()
+}
+
+/// Double-quotes and escapes `s` for a YAML double-quoted scalar. Ported
+/// from yaml_rust's own (private) `emitter::escape_str`, so the byte-level
+/// escaping (in particular control bytes other than the common `\n`/`\t`)
+/// matches what `YamlEmitter` would produce.
+fn escape_str(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    let mut start = 0;
+    for (i, byte) in s.bytes().enumerate() {
+        let escaped = match byte {
+            b'"' => "\\\"",
+            b'\\' => "\\\\",
+            b'\x00' => "\\u0000",
+            b'\x01' => "\\u0001",
+            b'\x02' => "\\u0002",
+            b'\x03' => "\\u0003",
+            b'\x04' => "\\u0004",
+            b'\x05' => "\\u0005",
+            b'\x06' => "\\u0006",
+            b'\x07' => "\\u0007",
+            b'\x08' => "\\b",
+            b'\t' => "\\t",
+            b'\n' => "\\n",
+            b'\x0b' => "\\u000b",
+            b'\x0c' => "\\f",
+            b'\r' => "\\r",
+            b'\x0e' => "\\u000e",
+            b'\x0f' => "\\u000f",
+            b'\x10' => "\\u0010",
+            b'\x11' => "\\u0011",
+            b'\x12' => "\\u0012",
+            b'\x13' => "\\u0013",
+            b'\x14' => "\\u0014",
+            b'\x15' => "\\u0015",
+            b'\x16' => "\\u0016",
+            b'\x17' => "\\u0017",
+            b'\x18' => "\\u0018",
+            b'\x19' => "\\u0019",
+            b'\x1a' => "\\u001a",
+            b'\x1b' => "\\u001b",
+            b'\x1c' => "\\u001c",
+            b'\x1d' => "\\u001d",
+            b'\x1e' => "\\u001e",
+            b'\x1f' => "\\u001f",
+            b'\x7f' => "\\u007f",
+            _ => continue,
+        };
+        if start < i {
+            out.push_str(&s[start..i]);
+        }
+        out.push_str(escaped);
+        start = i + 1;
+    }
+    if start != s.len() {
+        out.push_str(&s[start..]);
+    }
+    out.push('"');
+    out
+}
+
+/// Serializes `value` directly to `writer`, one YAML token at a time,
+/// without ever materializing an intermediate `Yaml` tree.
+///
+/// `WriterSerializer` tracks the current nesting depth and whether a `key:` or
+/// `-` was just written and is still waiting for its value (`pending_space`):
+/// a scalar consumes that wait by writing a single space before itself,
+/// while a nested block collection consumes it by breaking to a new,
+/// further-indented line instead. This lets every `serialize_*` method
+/// write straight through to `writer` in a single pass.
+pub struct WriterSerializer<W> {
+    writer: W,
+    depth: usize,
+    pending_space: bool,
+    bytes_repr: BytesRepr,
+    enum_repr: EnumRepr,
+}
+
+impl<W> WriterSerializer<W>
+    where W: io::Write
+{
+    pub fn new(writer: W) -> Self {
+        WriterSerializer::with_config(writer, BytesRepr::default(), EnumRepr::default())
+    }
+
+    /// Creates a `WriterSerializer` that represents byte buffers using
+    /// `bytes_repr` instead of the default per-byte integer array.
+    pub fn with_bytes_repr(writer: W, bytes_repr: BytesRepr) -> Self {
+        WriterSerializer::with_config(writer, bytes_repr, EnumRepr::default())
+    }
+
+    /// Creates a `WriterSerializer` that represents enum variants carrying a
+    /// payload using `enum_repr` instead of the default singleton map.
+    pub fn with_enum_repr(writer: W, enum_repr: EnumRepr) -> Self {
+        WriterSerializer::with_config(writer, BytesRepr::default(), enum_repr)
+    }
+
+    /// Creates a `WriterSerializer` with both `bytes_repr` and `enum_repr` set,
+    /// so the two knobs can be combined freely instead of being tied to
+    /// separate entry points.
+    pub fn with_config(writer: W, bytes_repr: BytesRepr, enum_repr: EnumRepr) -> Self {
+        WriterSerializer {
+            writer: writer,
+            depth: 0,
+            pending_space: false,
+            bytes_repr: bytes_repr,
+            enum_repr: enum_repr,
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    fn raw(&mut self, s: &str) -> Result<()> {
+        self.writer.write_all(s.as_bytes()).map_err(io_err)
+    }
+
+    fn write_indent(&mut self) -> Result<()> {
+        for _ in 0..(self.depth * 2) {
+            try!(self.raw(" "));
+        }
+        Ok(())
+    }
+
+    /// Writes a scalar, consuming any pending `key:`/`-` separator space.
+    fn write_scalar(&mut self, s: &str) -> Result<()> {
+        if self.pending_space {
+            try!(self.raw(" "));
+            self.pending_space = false;
+        }
+        self.raw(s)
+    }
+
+    fn write_str_scalar(&mut self, s: &str) -> Result<()> {
+        if need_quotes(s) {
+            self.write_scalar(&escape_str(s))
+        } else {
+            self.write_scalar(s)
+        }
+    }
+}
+
+impl<'a, W> ser::Serializer for &'a mut WriterSerializer<W>
+    where W: io::Write
+{
+    type Ok = ();
     type Error = Error;
 
-    type SerializeSeq = SerializeArray;
-    type SerializeTuple = SerializeArray;
-    type SerializeTupleStruct = SerializeArray;
-    type SerializeTupleVariant = SerializeTupleVariant;
-    type SerializeMap = SerializeMap;
-    type SerializeStruct = SerializeStruct;
-    type SerializeStructVariant = SerializeStructVariant;
+    type SerializeSeq = SeqSerializer<'a, W>;
+    type SerializeTuple = SeqSerializer<'a, W>;
+    type SerializeTupleStruct = SeqSerializer<'a, W>;
+    type SerializeTupleVariant = TupleVariantSerializer<'a, W>;
+    type SerializeMap = MapSerializer<'a, W>;
+    type SerializeStruct = MapSerializer<'a, W>;
+    type SerializeStructVariant = StructVariantSerializer<'a, W>;
 
-    fn serialize_bool(self, v: bool) -> Result<Yaml> {
-        Ok(Yaml::Boolean(v))
+    fn serialize_bool(self, v: bool) -> Result<()> {
+        self.write_scalar(if v { "true" } else { "false" })
     }
 
-    fn serialize_i8(self, v: i8) -> Result<Yaml> {
+    fn serialize_i8(self, v: i8) -> Result<()> {
         self.serialize_i64(v as i64)
     }
 
-    fn serialize_i16(self, v: i16) -> Result<Yaml> {
+    fn serialize_i16(self, v: i16) -> Result<()> {
         self.serialize_i64(v as i64)
     }
 
-    fn serialize_i32(self, v: i32) -> Result<Yaml> {
+    fn serialize_i32(self, v: i32) -> Result<()> {
         self.serialize_i64(v as i64)
     }
 
-    fn serialize_i64(self, v: i64) -> Result<Yaml> {
-        Ok(Yaml::Integer(v))
+    fn serialize_i64(self, v: i64) -> Result<()> {
+        self.write_scalar(&v.to_string())
     }
 
-    fn serialize_u8(self, v: u8) -> Result<Yaml> {
+    fn serialize_u8(self, v: u8) -> Result<()> {
         self.serialize_i64(v as i64)
     }
 
-    fn serialize_u16(self, v: u16) -> Result<Yaml> {
+    fn serialize_u16(self, v: u16) -> Result<()> {
         self.serialize_i64(v as i64)
     }
 
-    fn serialize_u32(self, v: u32) -> Result<Yaml> {
+    fn serialize_u32(self, v: u32) -> Result<()> {
         self.serialize_i64(v as i64)
     }
 
-    fn serialize_u64(self, v: u64) -> Result<Yaml> {
+    fn serialize_u64(self, v: u64) -> Result<()> {
         self.serialize_i64(v as i64)
     }
 
-    fn serialize_f32(self, v: f32) -> Result<Yaml> {
+    fn serialize_f32(self, v: f32) -> Result<()> {
         self.serialize_f64(v as f64)
     }
 
-    fn serialize_f64(self, v: f64) -> Result<Yaml> {
-        Ok(Yaml::Real(v.to_string()))
+    fn serialize_f64(self, v: f64) -> Result<()> {
+        self.write_scalar(&float_repr(v))
     }
 
-    fn serialize_char(self, value: char) -> Result<Yaml> {
-        Ok(Yaml::String(value.to_string()))
+    fn serialize_char(self, value: char) -> Result<()> {
+        let mut buf = [0u8; 4];
+        self.serialize_str(value.encode_utf8(&mut buf))
     }
 
-    fn serialize_str(self, value: &str) -> Result<Yaml> {
-        Ok(Yaml::String(value.to_owned()))
+    fn serialize_str(self, value: &str) -> Result<()> {
+        self.write_str_scalar(value)
     }
 
-    fn serialize_bytes(self, value: &[u8]) -> Result<Yaml> {
-        let vec = value.iter().map(|&b| Yaml::Integer(b as i64)).collect();
-        Ok(Yaml::Array(vec))
+    fn serialize_bytes(self, value: &[u8]) -> Result<()> {
+        if self.bytes_repr == BytesRepr::Binary {
+            try!(self.write_scalar("!!binary "));
+            return self.raw(&base64_encode(value));
+        }
+        // Block-sequence form (one `- N` per line), matching the
+        // pre-streaming tree-based emitter's rendering of a byte array.
+        let mut seq = try!(self.serialize_seq(Some(value.len())));
+        for b in value {
+            try!(ser::SerializeSeq::serialize_element(&mut seq, b));
+        }
+        ser::SerializeSeq::end(seq)
     }
 
-    fn serialize_unit(self) -> Result<Yaml> {
-        Ok(Yaml::Null)
+    fn serialize_unit(self) -> Result<()> {
+        self.write_scalar("~")
     }
 
-    fn serialize_unit_struct(self, _name: &'static str) -> Result<Yaml> {
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<()> {
         self.serialize_unit()
     }
 
@@ -102,15 +376,19 @@ impl ser::Serializer for Serializer {
         _name: &str,
         _variant_index: usize,
         variant: &str
-    ) -> Result<Yaml> {
-        Ok(Yaml::String(variant.to_owned()))
+    ) -> Result<()> {
+        match self.enum_repr {
+            EnumRepr::Map => self.write_str_scalar(variant),
+            EnumRepr::Tag => self.write_scalar(&format!("!{}", variant)),
+            EnumRepr::Untagged => self.serialize_unit(),
+        }
     }
 
     fn serialize_newtype_struct<T: ?Sized>(
         self,
         _name: &'static str,
         value: &T
-    ) -> Result<Yaml>
+    ) -> Result<()>
         where T: ser::Serialize
     {
         value.serialize(self)
@@ -122,35 +400,32 @@ impl ser::Serializer for Serializer {
         _variant_index: usize,
         variant: &str,
         value: &T
-    ) -> Result<Yaml>
+    ) -> Result<()>
         where T: ser::Serialize
     {
-        Ok(singleton_hash(try!(to_yaml(variant)), try!(to_yaml(value))))
+        try!(write_variant_prefix(self, variant));
+        value.serialize(self)
     }
 
-    fn serialize_none(self) -> Result<Yaml> {
+    fn serialize_none(self) -> Result<()> {
         self.serialize_unit()
     }
 
-    fn serialize_some<V: ?Sized>(self, value: &V) -> Result<Yaml>
+    fn serialize_some<V: ?Sized>(self, value: &V) -> Result<()>
         where V: ser::Serialize
     {
         value.serialize(self)
     }
 
-    fn serialize_seq(self, len: Option<usize>) -> Result<SerializeArray> {
-        let array = match len {
-            None => yaml::Array::new(),
-            Some(len) => yaml::Array::with_capacity(len),
-        };
-        Ok(SerializeArray { array: array })
+    fn serialize_seq(self, _len: Option<usize>) -> Result<SeqSerializer<'a, W>> {
+        Ok(SeqSerializer { ser: self, wrote_anything: false, indented: false })
     }
 
-    fn serialize_seq_fixed_size(self, len: usize) -> Result<SerializeArray> {
+    fn serialize_seq_fixed_size(self, len: usize) -> Result<SeqSerializer<'a, W>> {
         self.serialize_seq(Some(len))
     }
 
-    fn serialize_tuple(self, len: usize) -> Result<SerializeArray> {
+    fn serialize_tuple(self, len: usize) -> Result<SeqSerializer<'a, W>> {
         self.serialize_seq(Some(len))
     }
 
@@ -158,7 +433,7 @@ impl ser::Serializer for Serializer {
         self,
         _name: &'static str,
         len: usize
-    ) -> Result<SerializeArray> {
+    ) -> Result<SeqSerializer<'a, W>> {
         self.serialize_seq(Some(len))
     }
 
@@ -167,21 +442,22 @@ impl ser::Serializer for Serializer {
         _enum: &'static str,
         _idx: usize,
         variant: &'static str,
-        len: usize
-    ) -> Result<SerializeTupleVariant> {
-        Ok(SerializeTupleVariant { name: variant, array: yaml::Array::with_capacity(len) })
+        _len: usize
+    ) -> Result<TupleVariantSerializer<'a, W>> {
+        try!(write_variant_prefix(self, variant));
+        Ok(TupleVariantSerializer { ser: self, wrote_anything: false, indented: false })
     }
 
-    fn serialize_map(self, _len: Option<usize>) -> Result<SerializeMap> {
-        Ok(SerializeMap { hash: yaml::Hash::new(), next_key: None })
+    fn serialize_map(self, _len: Option<usize>) -> Result<MapSerializer<'a, W>> {
+        Ok(MapSerializer { ser: self, wrote_anything: false, indented: false })
     }
 
     fn serialize_struct(
         self,
         _name: &'static str,
         _len: usize
-    ) -> Result<SerializeStruct> {
-        Ok(SerializeStruct { hash: yaml::Hash::new() })
+    ) -> Result<MapSerializer<'a, W>> {
+        self.serialize_map(None)
     }
 
     fn serialize_struct_variant(
@@ -190,169 +466,318 @@ impl ser::Serializer for Serializer {
         _idx: usize,
         variant: &'static str,
         _len: usize
-    ) -> Result<SerializeStructVariant> {
-        Ok(SerializeStructVariant { name: variant, hash: yaml::Hash::new() })
+    ) -> Result<StructVariantSerializer<'a, W>> {
+        try!(write_variant_prefix(self, variant));
+        Ok(StructVariantSerializer { ser: self, wrote_anything: false, indented: false })
     }
 }
 
-#[doc(hidden)]
-pub struct SerializeArray {
-    array: yaml::Array,
+/// Writes the prefix that precedes an enum variant's payload, according to
+/// `ser.enum_repr`: `variant:` for `Map`, `!variant` for `Tag`, or nothing
+/// at all for `Untagged`. Leaves `pending_space` set for `Map`/`Tag` so the
+/// payload that follows is nested under it exactly like a `key:` value;
+/// `Untagged` leaves it unset so the payload takes the variant's place
+/// with no extra nesting.
+fn write_variant_prefix<W>(ser: &mut WriterSerializer<W>, variant: &str) -> Result<()>
+    where W: io::Write
+{
+    match ser.enum_repr {
+        EnumRepr::Map => {
+            try!(ser.write_str_scalar(variant));
+            try!(ser.raw(":"));
+            ser.pending_space = true;
+        }
+        EnumRepr::Tag => {
+            try!(ser.write_scalar(&format!("!{}", variant)));
+            ser.pending_space = true;
+        }
+        EnumRepr::Untagged => {}
+    }
+    Ok(())
 }
 
-#[doc(hidden)]
-pub struct SerializeTupleVariant {
-    name: &'static str,
-    array: yaml::Array,
+/// Breaks to a new line for the first element of a nested block
+/// collection, consuming the separator space left pending by the parent
+/// `key:`/`-` and indenting one level deeper. The root document has no
+/// pending `key:`/`-` to consume, so it starts directly at the current
+/// depth instead of gaining a spurious leading line. Subsequent elements
+/// just start a new line at the same depth. Sets `indented` if this call
+/// bumped `depth`, so the caller's `end()` can undo exactly that.
+fn begin_block_entry<W>(
+    ser: &mut WriterSerializer<W>,
+    wrote_anything: &mut bool,
+    indented: &mut bool
+) -> Result<()>
+    where W: io::Write
+{
+    if *wrote_anything {
+        try!(ser.raw("\n"));
+    } else {
+        *wrote_anything = true;
+        if ser.pending_space {
+            ser.pending_space = false;
+            try!(ser.raw("\n"));
+            ser.depth += 1;
+            *indented = true;
+        }
+    }
+    ser.write_indent()
 }
 
 #[doc(hidden)]
-pub struct SerializeMap {
-    hash: yaml::Hash,
-    next_key: Option<yaml::Yaml>,
+pub struct SeqSerializer<'a, W: io::Write + 'a> {
+    ser: &'a mut WriterSerializer<W>,
+    wrote_anything: bool,
+    indented: bool,
 }
 
-#[doc(hidden)]
-pub struct SerializeStruct {
-    hash: yaml::Hash,
-}
+impl<'a, W> SeqSerializer<'a, W>
+    where W: io::Write
+{
+    fn element<T: ?Sized>(&mut self, value: &T) -> Result<()>
+        where T: ser::Serialize
+    {
+        try!(begin_block_entry(self.ser, &mut self.wrote_anything, &mut self.indented));
+        try!(self.ser.raw("-"));
+        self.ser.pending_space = true;
+        value.serialize(&mut *self.ser)
+    }
 
-#[doc(hidden)]
-pub struct SerializeStructVariant {
-    name: &'static str,
-    hash: yaml::Hash,
+    fn finish(self) -> Result<()> {
+        if self.wrote_anything {
+            if self.indented {
+                self.ser.depth -= 1;
+            }
+            Ok(())
+        } else {
+            self.ser.write_scalar("[]")
+        }
+    }
 }
 
-impl ser::SerializeSeq for SerializeArray {
-    type Ok = yaml::Yaml;
+impl<'a, W> ser::SerializeSeq for SeqSerializer<'a, W>
+    where W: io::Write
+{
+    type Ok = ();
     type Error = Error;
 
-    fn serialize_element<T: ?Sized>(&mut self, elem: &T) -> Result<()>
+    fn serialize_element<T: ?Sized>(&mut self, value: &T) -> Result<()>
         where T: ser::Serialize
     {
-        self.array.push(try!(to_yaml(elem)));
-        Ok(())
+        self.element(value)
     }
 
-    fn end(self) -> Result<Yaml> {
-        Ok(Yaml::Array(self.array))
+    fn end(self) -> Result<()> {
+        self.finish()
     }
 }
 
-impl ser::SerializeTuple for SerializeArray {
-    type Ok = yaml::Yaml;
+impl<'a, W> ser::SerializeTuple for SeqSerializer<'a, W>
+    where W: io::Write
+{
+    type Ok = ();
     type Error = Error;
 
-    fn serialize_element<T: ?Sized>(&mut self, elem: &T) -> Result<()>
+    fn serialize_element<T: ?Sized>(&mut self, value: &T) -> Result<()>
         where T: ser::Serialize
     {
-        ser::SerializeSeq::serialize_element(self, elem)
+        self.element(value)
     }
 
-    fn end(self) -> Result<Yaml> {
-        ser::SerializeSeq::end(self)
+    fn end(self) -> Result<()> {
+        self.finish()
     }
 }
 
-impl ser::SerializeTupleStruct for SerializeArray {
-    type Ok = yaml::Yaml;
+impl<'a, W> ser::SerializeTupleStruct for SeqSerializer<'a, W>
+    where W: io::Write
+{
+    type Ok = ();
     type Error = Error;
 
     fn serialize_field<V: ?Sized>(&mut self, value: &V) -> Result<()>
         where V: ser::Serialize
     {
-        ser::SerializeSeq::serialize_element(self, value)
+        self.element(value)
     }
 
-    fn end(self) -> Result<Yaml> {
-        ser::SerializeSeq::end(self)
+    fn end(self) -> Result<()> {
+        self.finish()
     }
 }
 
-impl ser::SerializeTupleVariant for SerializeTupleVariant {
-    type Ok = yaml::Yaml;
+#[doc(hidden)]
+pub struct TupleVariantSerializer<'a, W: io::Write + 'a> {
+    ser: &'a mut WriterSerializer<W>,
+    wrote_anything: bool,
+    indented: bool,
+}
+
+impl<'a, W> ser::SerializeTupleVariant for TupleVariantSerializer<'a, W>
+    where W: io::Write
+{
+    type Ok = ();
     type Error = Error;
 
-    fn serialize_field<V: ?Sized>(&mut self, v: &V) -> Result<()>
+    fn serialize_field<V: ?Sized>(&mut self, value: &V) -> Result<()>
         where V: ser::Serialize
     {
-        self.array.push(try!(to_yaml(v)));
-        Ok(())
+        try!(begin_block_entry(self.ser, &mut self.wrote_anything, &mut self.indented));
+        try!(self.ser.raw("-"));
+        self.ser.pending_space = true;
+        value.serialize(&mut *self.ser)
     }
 
-    fn end(self) -> Result<Yaml> {
-        Ok(singleton_hash(try!(to_yaml(self.name)), Yaml::Array(self.array)))
+    fn end(self) -> Result<()> {
+        if self.wrote_anything {
+            if self.indented {
+                self.ser.depth -= 1;
+            }
+            Ok(())
+        } else {
+            self.ser.write_scalar("[]")
+        }
     }
 }
 
-impl ser::SerializeMap for SerializeMap {
-    type Ok = yaml::Yaml;
+#[doc(hidden)]
+pub struct MapSerializer<'a, W: io::Write + 'a> {
+    ser: &'a mut WriterSerializer<W>,
+    wrote_anything: bool,
+    indented: bool,
+}
+
+impl<'a, W> ser::SerializeMap for MapSerializer<'a, W>
+    where W: io::Write
+{
+    type Ok = ();
     type Error = Error;
 
     fn serialize_key<T: ?Sized>(&mut self, key: &T) -> Result<()>
         where T: ser::Serialize
     {
-        self.next_key = Some(try!(to_yaml(key)));
-        Ok(())
+        try!(begin_block_entry(self.ser, &mut self.wrote_anything, &mut self.indented));
+        key.serialize(&mut *self.ser)
     }
 
     fn serialize_value<T: ?Sized>(&mut self, value: &T) -> Result<()>
         where T: ser::Serialize
     {
-        match self.next_key.take() {
-            Some(key) => self.hash.insert(key, try!(to_yaml(value))),
-            None => panic!("serialize_value called before serialize_key"),
-        };
-        Ok(())
+        try!(self.ser.raw(":"));
+        self.ser.pending_space = true;
+        value.serialize(&mut *self.ser)
     }
 
-    fn end(self) -> Result<Yaml> {
-        Ok(Yaml::Hash(self.hash))
+    fn end(self) -> Result<()> {
+        if self.wrote_anything {
+            if self.indented {
+                self.ser.depth -= 1;
+            }
+            Ok(())
+        } else {
+            self.ser.write_scalar("{}")
+        }
     }
 }
 
-impl ser::SerializeStruct for SerializeStruct {
-    type Ok = yaml::Yaml;
+impl<'a, W> ser::SerializeStruct for MapSerializer<'a, W>
+    where W: io::Write
+{
+    type Ok = ();
     type Error = Error;
 
     fn serialize_field<V: ?Sized>(&mut self, key: &'static str, value: &V) -> Result<()>
         where V: ser::Serialize
     {
-        self.hash.insert(try!(to_yaml(key)), try!(to_yaml(value)));
-        Ok(())
+        try!(begin_block_entry(self.ser, &mut self.wrote_anything, &mut self.indented));
+        try!(self.ser.write_str_scalar(key));
+        try!(self.ser.raw(":"));
+        self.ser.pending_space = true;
+        value.serialize(&mut *self.ser)
     }
 
-    fn end(self) -> Result<Yaml> {
-        Ok(Yaml::Hash(self.hash))
+    fn end(self) -> Result<()> {
+        if self.wrote_anything {
+            if self.indented {
+                self.ser.depth -= 1;
+            }
+            Ok(())
+        } else {
+            self.ser.write_scalar("{}")
+        }
     }
 }
 
-impl ser::SerializeStructVariant for SerializeStructVariant {
-    type Ok = yaml::Yaml;
+#[doc(hidden)]
+pub struct StructVariantSerializer<'a, W: io::Write + 'a> {
+    ser: &'a mut WriterSerializer<W>,
+    wrote_anything: bool,
+    indented: bool,
+}
+
+impl<'a, W> ser::SerializeStructVariant for StructVariantSerializer<'a, W>
+    where W: io::Write
+{
+    type Ok = ();
     type Error = Error;
 
-    fn serialize_field<V: ?Sized>(&mut self, field: &'static str, v: &V) -> Result<()>
+    fn serialize_field<V: ?Sized>(&mut self, field: &'static str, value: &V) -> Result<()>
         where V: ser::Serialize
     {
-        self.hash.insert(try!(to_yaml(field)), try!(to_yaml(v)));
-        Ok(())
+        try!(begin_block_entry(self.ser, &mut self.wrote_anything, &mut self.indented));
+        try!(self.ser.write_str_scalar(field));
+        try!(self.ser.raw(":"));
+        self.ser.pending_space = true;
+        value.serialize(&mut *self.ser)
     }
 
-    fn end(self) -> Result<Yaml> {
-        Ok(singleton_hash(try!(to_yaml(self.name)), Yaml::Hash(self.hash)))
+    fn end(self) -> Result<()> {
+        if self.wrote_anything {
+            if self.indented {
+                self.ser.depth -= 1;
+            }
+            Ok(())
+        } else {
+            self.ser.write_scalar("{}")
+        }
     }
 }
 
+/// Serializes `value` as YAML into `writer` in a single pass, using
+/// `bytes_repr`/`enum_repr` to control how byte buffers and enum variant
+/// payloads are represented, without ever building an intermediate `Yaml`
+/// tree.
+///
+/// This is the shared implementation behind [`to_writer`],
+/// [`to_writer_with_bytes_repr`], [`to_writer_with_enum_repr`], and
+/// [`to_writer_with_config`]: both knobs live on the one streaming
+/// `WriterSerializer`, so they compose freely instead of being tied to separate,
+/// mutually exclusive entry points.
+///
+/// Like `yaml_rust::YamlEmitter` (which this crate used exclusively before
+/// the streaming rewrite), the output starts with the `---` document-start
+/// marker.
+fn to_writer_streaming<W, T>(
+    writer: &mut W,
+    value: &T,
+    bytes_repr: BytesRepr,
+    enum_repr: EnumRepr
+) -> Result<()>
+    where W: io::Write,
+          T: ser::Serialize
+{
+    try!(writer.write_all(b"---\n").map_err(io_err));
+    let mut ser = WriterSerializer::with_config(writer, bytes_repr, enum_repr);
+    value.serialize(&mut ser)
+}
+
+/// Serializes `value` as YAML into `writer` in a single pass, without
+/// building an intermediate `Yaml` tree.
 pub fn to_writer<W, T>(writer: &mut W, value: &T) -> Result<()>
     where W: io::Write,
           T: ser::Serialize
 {
-    let doc = try!(to_yaml(value));
-    let mut writer_adapter = FmtToIoWriter {
-        writer: writer,
-    };
-    try!(YamlEmitter::new(&mut writer_adapter).dump(&doc));
-    Ok(())
+    to_writer_streaming(writer, value, BytesRepr::default(), EnumRepr::default())
 }
 
 pub fn to_vec<T>(value: &T) -> Result<Vec<u8>>
@@ -369,33 +794,1478 @@ pub fn to_string<T>(value: &T) -> Result<String>
     Ok(try!(String::from_utf8(try!(to_vec(value)))))
 }
 
-/// The yaml-rust library uses `fmt.Write` intead of `io.Write` so this is a
-/// simple adapter.
-struct FmtToIoWriter<'a, W>
-    where W: io::Write + 'a
+/// Serializes `value` as YAML into `writer`, representing byte buffers
+/// using `bytes_repr` instead of the default per-byte integer array.
+pub fn to_writer_with_bytes_repr<W, T>(writer: &mut W, value: &T, bytes_repr: BytesRepr) -> Result<()>
+    where W: io::Write,
+          T: ser::Serialize
 {
-    writer: &'a mut W,
+    to_writer_streaming(writer, value, bytes_repr, EnumRepr::default())
 }
 
-impl<'a, W> fmt::Write for FmtToIoWriter<'a, W>
-    where W: io::Write + 'a
+/// Serializes `value` as a YAML string, representing byte buffers using
+/// `bytes_repr` instead of the default per-byte integer array.
+pub fn to_string_with_bytes_repr<T>(value: &T, bytes_repr: BytesRepr) -> Result<String>
+    where T: ser::Serialize
 {
-    fn write_str(&mut self, s: &str) -> fmt::Result {
-        if self.writer.write(s.as_bytes()).is_err() {
-            return Err(fmt::Error);
+    let mut vec = Vec::with_capacity(128);
+    try!(to_writer_with_bytes_repr(&mut vec, value, bytes_repr));
+    Ok(try!(String::from_utf8(vec)))
+}
+
+/// Serializes `value` as YAML into `writer`, representing enum variants
+/// with a payload using `enum_repr` instead of the default singleton map.
+pub fn to_writer_with_enum_repr<W, T>(writer: &mut W, value: &T, enum_repr: EnumRepr) -> Result<()>
+    where W: io::Write,
+          T: ser::Serialize
+{
+    to_writer_streaming(writer, value, BytesRepr::default(), enum_repr)
+}
+
+/// Serializes `value` as a YAML string, representing enum variants with a
+/// payload using `enum_repr` instead of the default singleton map.
+pub fn to_string_with_enum_repr<T>(value: &T, enum_repr: EnumRepr) -> Result<String>
+    where T: ser::Serialize
+{
+    let mut vec = Vec::with_capacity(128);
+    try!(to_writer_with_enum_repr(&mut vec, value, enum_repr));
+    Ok(try!(String::from_utf8(vec)))
+}
+
+/// Serializes `value` as YAML into `writer`, combining `bytes_repr` and
+/// `enum_repr` so both can be customized at once instead of only through
+/// one of the single-knob helpers above.
+pub fn to_writer_with_config<W, T>(
+    writer: &mut W,
+    value: &T,
+    bytes_repr: BytesRepr,
+    enum_repr: EnumRepr
+) -> Result<()>
+    where W: io::Write,
+          T: ser::Serialize
+{
+    to_writer_streaming(writer, value, bytes_repr, enum_repr)
+}
+
+/// Serializes `value` as a YAML string, combining `bytes_repr` and
+/// `enum_repr` so both can be customized at once instead of only through
+/// one of the single-knob helpers above.
+pub fn to_string_with_config<T>(value: &T, bytes_repr: BytesRepr, enum_repr: EnumRepr) -> Result<String>
+    where T: ser::Serialize
+{
+    let mut vec = Vec::with_capacity(128);
+    try!(to_writer_with_config(&mut vec, value, bytes_repr, enum_repr));
+    Ok(try!(String::from_utf8(vec)))
+}
+
+/// Configuration for [`to_writer_pretty`] and [`to_string_pretty`].
+///
+/// `YamlEmitter` from `yaml_rust` does not let callers tweak indentation or
+/// collection layout, so this crate carries its own small emitter (see
+/// `Emitter` below) that understands the `best_indent`, `compact`, and
+/// `color` knobs; `enum_repr` and `bytes_repr` shape the tree it emits.
+#[derive(Clone, Copy, Debug)]
+pub struct EmitterConfig {
+    /// Number of spaces used per indentation level.
+    pub best_indent: usize,
+    /// Keep a nested block sequence at the same indent as its parent key
+    /// instead of indenting it one level deeper. Nested mappings always
+    /// indent one level deeper, since unlike a sequence's `-` they have no
+    /// marker of their own to set them apart from the parent's remaining
+    /// keys.
+    pub compact: bool,
+    /// Wrap keys, scalars, and structural punctuation in ANSI escape codes
+    /// so the output is readable on a terminal.
+    pub color: bool,
+    /// How to represent enum variants carrying a payload. Threaded through
+    /// to the tree this pretty-printer builds, so pretty output can use
+    /// `EnumRepr::Tag`/`EnumRepr::Untagged` just like the streaming
+    /// `to_writer_with_enum_repr` can.
+    pub enum_repr: EnumRepr,
+    /// How to represent byte buffers. Threaded through to the tree this
+    /// pretty-printer builds, so pretty output can use `BytesRepr::Binary`
+    /// just like the streaming `to_writer_with_bytes_repr` can.
+    pub bytes_repr: BytesRepr,
+}
+
+impl Default for EmitterConfig {
+    fn default() -> Self {
+        EmitterConfig {
+            best_indent: 2,
+            compact: true,
+            color: false,
+            enum_repr: EnumRepr::default(),
+            bytes_repr: BytesRepr::default(),
         }
-        Ok(())
     }
 }
 
-fn to_yaml<T>(elem: T) -> Result<Yaml>
-    where T: ser::Serialize
+const COLOR_RESET: &'static str = "\x1b[0m";
+const COLOR_KEY: &'static str = "\x1b[36m";
+const COLOR_STRING: &'static str = "\x1b[32m";
+const COLOR_SCALAR: &'static str = "\x1b[33m";
+const COLOR_PUNCT: &'static str = "\x1b[2m";
+
+/// A fork of `yaml_rust::YamlEmitter` that adds configurable indentation,
+/// compact nested collections, and optional ANSI coloring.
+struct Emitter<'a, W>
+    where W: fmt::Write + 'a
 {
-    elem.serialize(Serializer)
+    writer: &'a mut W,
+    config: EmitterConfig,
 }
 
-fn singleton_hash(k: Yaml, v: Yaml) -> Yaml {
+fn fmt_err(_: fmt::Error) -> Error {
+    Error::custom("failed to write YAML output")
+}
+
+impl<'a, W> Emitter<'a, W>
+    where W: fmt::Write + 'a
+{
+    fn new(writer: &'a mut W, config: EmitterConfig) -> Self {
+        Emitter { writer: writer, config: config }
+    }
+
+    fn write_colored(&mut self, color: &str, text: &str) -> Result<()> {
+        if self.config.color {
+            try!(write!(self.writer, "{}{}{}", color, text, COLOR_RESET).map_err(fmt_err));
+        } else {
+            try!(write!(self.writer, "{}", text).map_err(fmt_err));
+        }
+        Ok(())
+    }
+
+    fn write_indent(&mut self, level: usize) -> Result<()> {
+        for _ in 0..(level * self.config.best_indent) {
+            try!(write!(self.writer, " ").map_err(fmt_err));
+        }
+        Ok(())
+    }
+
+    fn dump(&mut self, doc: &Node) -> Result<()> {
+        // Every other public entry point in this file (`to_writer`,
+        // `to_writer_with_enum_repr`, ...) writes the `---` document-start
+        // marker that `yaml_rust::YamlEmitter` always wrote; match that here
+        // instead of silently diverging.
+        try!(write!(self.writer, "---\n").map_err(fmt_err));
+        self.emit_node(doc, 0, false)
+    }
+
+    fn emit_node(&mut self, node: &Node, level: usize, is_key: bool) -> Result<()> {
+        match *node {
+            Node::Array(ref v) => self.emit_array(v, level),
+            Node::Hash(ref h) => self.emit_hash(h, level),
+            Node::Tagged(ref variant, ref payload) => self.emit_tagged(variant, payload, level),
+            Node::Binary(ref base64) => self.emit_binary(base64),
+            _ => self.emit_scalar(node, is_key),
+        }
+    }
+
+    /// Writes a `!!binary`-tagged scalar with `base64` as its unquoted
+    /// content. `base64`'s alphabet (`A-Za-z0-9+/=`) overlaps with
+    /// `need_quotes`'s flow-indicator set (`=`), so this bypasses
+    /// `scalar_repr`/`need_quotes` entirely rather than risk the payload
+    /// being double-quoted like an ordinary string scalar.
+    fn emit_binary(&mut self, base64: &str) -> Result<()> {
+        try!(self.write_colored(COLOR_PUNCT, "!!binary "));
+        self.write_colored(COLOR_SCALAR, base64)
+    }
+
+    fn emit_tagged(&mut self, variant: &str, payload: &Node, level: usize) -> Result<()> {
+        try!(self.write_colored(COLOR_PUNCT, "!"));
+        try!(self.write_colored(COLOR_KEY, variant));
+        match *payload {
+            Node::Null => Ok(()),
+            Node::Array(ref nested) if !nested.is_empty() => {
+                try!(write!(self.writer, "\n").map_err(fmt_err));
+                let child_level = if self.config.compact { level } else { level + 1 };
+                self.emit_array(nested, child_level)
+            }
+            Node::Hash(ref nested) if !nested.is_empty() => {
+                try!(write!(self.writer, "\n").map_err(fmt_err));
+                self.emit_hash(nested, level + 1)
+            }
+            _ => {
+                try!(write!(self.writer, " ").map_err(fmt_err));
+                self.emit_node(payload, level, false)
+            }
+        }
+    }
+
+    fn emit_scalar(&mut self, node: &Node, is_key: bool) -> Result<()> {
+        let repr = scalar_repr(node);
+        let color = if is_key {
+            COLOR_KEY
+        } else if let Node::String(_) = *node {
+            COLOR_STRING
+        } else {
+            COLOR_SCALAR
+        };
+        self.write_colored(color, &repr)
+    }
+
+    fn emit_array(&mut self, v: &[Node], level: usize) -> Result<()> {
+        if v.is_empty() {
+            return self.write_colored(COLOR_PUNCT, "[]");
+        }
+        for (i, item) in v.iter().enumerate() {
+            if i > 0 {
+                try!(write!(self.writer, "\n").map_err(fmt_err));
+            }
+            try!(self.write_indent(level));
+            try!(self.write_colored(COLOR_PUNCT, "-"));
+            try!(self.emit_collection_value(item, level));
+        }
+        Ok(())
+    }
+
+    fn emit_hash(&mut self, h: &[(Node, Node)], level: usize) -> Result<()> {
+        if h.is_empty() {
+            return self.write_colored(COLOR_PUNCT, "{}");
+        }
+        for (i, &(ref k, ref v)) in h.iter().enumerate() {
+            if i > 0 {
+                try!(write!(self.writer, "\n").map_err(fmt_err));
+            }
+            try!(self.write_indent(level));
+            try!(self.emit_node(k, level, true));
+            try!(self.write_colored(COLOR_PUNCT, ":"));
+            try!(self.emit_collection_value(v, level));
+        }
+        Ok(())
+    }
+
+    /// Writes the value that follows a `-` or `key:` prefix: nested block
+    /// collections break to a new line, tagged nodes and scalars stay on
+    /// the current line. A nested sequence is kept at the same indent as
+    /// its parent unless `compact` is disabled, since the `-` marker
+    /// already disambiguates it; a nested mapping always indents one
+    /// level deeper regardless of `compact`, since without a marker of
+    /// its own it would otherwise be indistinguishable from its parent's
+    /// remaining keys.
+    ///
+    /// Newlines only ever separate entries (written by `emit_array`/
+    /// `emit_hash` before all but the first), never terminate them, so
+    /// nothing written by `Emitter` ends in a trailing newline -- matching
+    /// `to_string`, which never emits one either. `emit_tagged`'s inline
+    /// scalar arm relies on this same rule.
+    fn emit_collection_value(&mut self, v: &Node, level: usize) -> Result<()> {
+        match *v {
+            Node::Array(ref nested) if !nested.is_empty() => {
+                try!(write!(self.writer, "\n").map_err(fmt_err));
+                let child_level = if self.config.compact { level } else { level + 1 };
+                self.emit_array(nested, child_level)
+            }
+            Node::Hash(ref nested) if !nested.is_empty() => {
+                try!(write!(self.writer, "\n").map_err(fmt_err));
+                self.emit_hash(nested, level + 1)
+            }
+            _ => {
+                try!(write!(self.writer, " ").map_err(fmt_err));
+                self.emit_node(v, level, false)
+            }
+        }
+    }
+}
+
+fn scalar_repr(node: &Node) -> String {
+    match *node {
+        Node::Null => "~".to_owned(),
+        Node::Boolean(b) => if b { "true".to_owned() } else { "false".to_owned() },
+        Node::Integer(i) => i.to_string(),
+        Node::Real(ref s) => s.clone(),
+        Node::String(ref s) => {
+            if need_quotes(s) {
+                escape_str(s)
+            } else {
+                s.clone()
+            }
+        }
+        _ => "~".to_owned(),
+    }
+}
+
+/// The yaml-rust library uses `fmt.Write` intead of `io.Write` so this is a
+/// simple adapter.
+struct FmtToIoWriter<'a, W>
+    where W: io::Write + 'a
+{
+    writer: &'a mut W,
+}
+
+impl<'a, W> fmt::Write for FmtToIoWriter<'a, W>
+    where W: io::Write + 'a
+{
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        if self.writer.write(s.as_bytes()).is_err() {
+            return Err(fmt::Error);
+        }
+        Ok(())
+    }
+}
+
+/// Serializes `value` as YAML into `writer`, using `config` to control
+/// indentation, compactness, ANSI coloring, and enum variant representation.
+pub fn to_writer_pretty<W, T>(writer: &mut W, value: &T, config: EmitterConfig) -> Result<()>
+    where W: io::Write,
+          T: ser::Serialize
+{
+    let doc = try!(to_node(value, TreeConfig { enum_repr: config.enum_repr, bytes_repr: config.bytes_repr }));
+    let mut writer_adapter = FmtToIoWriter {
+        writer: writer,
+    };
+    let mut emitter = Emitter::new(&mut writer_adapter, config);
+    emitter.dump(&doc)
+}
+
+/// Serializes `value` as a YAML byte vector, using `config` to control
+/// indentation, compactness, and ANSI coloring.
+pub fn to_vec_pretty<T>(value: &T, config: EmitterConfig) -> Result<Vec<u8>>
+    where T: ser::Serialize
+{
+    let mut vec = Vec::with_capacity(128);
+    try!(to_writer_pretty(&mut vec, value, config));
+    Ok(vec)
+}
+
+/// Serializes `value` as a YAML string, using `config` to control
+/// indentation, compactness, and ANSI coloring.
+pub fn to_string_pretty<T>(value: &T, config: EmitterConfig) -> Result<String>
+    where T: ser::Serialize
+{
+    Ok(try!(String::from_utf8(try!(to_vec_pretty(value, config)))))
+}
+
+/// Bundles the two tree-building knobs (`enum_repr`, `bytes_repr`) so they
+/// thread through `to_yaml`/`Serializer` together instead of one at a
+/// time; without this, adding a second knob to the recursive `to_yaml`
+/// calls below would mean updating every call site twice.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct TreeConfig {
+    pub enum_repr: EnumRepr,
+    pub bytes_repr: BytesRepr,
+}
+
+/// Builds a complete `Yaml` value for `elem`, representing enum variants
+/// and byte buffers according to `config`. Backs the public `Serializer`
+/// below; `to_writer_pretty`/`to_string_pretty` use [`to_node`] instead, so
+/// `Emitter` never has to shape-match tagged/binary nodes out of a
+/// `Yaml::Hash` (see [`Node`]).
+fn to_yaml<T>(elem: T, config: TreeConfig) -> Result<Yaml>
+    where T: ser::Serialize
+{
+    elem.serialize(Serializer::with_config(config))
+}
+
+/// Serializes a value directly into a `yaml_rust::Yaml` tree, with no
+/// document-level wrapping (no `---` marker, no byte writing). This is the
+/// crate's public entry point for callers that want a `Yaml` value rather
+/// than written-out bytes; `to_writer_pretty`/`to_string_pretty` build a
+/// [`Node`] tree internally instead (see [`to_node`]).
+#[derive(Clone, Copy, Default, Debug)]
+pub struct Serializer {
+    config: TreeConfig,
+}
+
+impl Serializer {
+    /// Creates a `Serializer` using the default `TreeConfig` (`EnumRepr::Map`,
+    /// `BytesRepr::Array`).
+    pub fn new() -> Self {
+        Serializer::default()
+    }
+
+    /// Creates a `Serializer` that represents enum variants and byte
+    /// buffers according to `config`.
+    pub fn with_config(config: TreeConfig) -> Self {
+        Serializer { config: config }
+    }
+}
+
+impl ser::Serializer for Serializer {
+    type Ok = Yaml;
+    type Error = Error;
+
+    type SerializeSeq = SerializeArray;
+    type SerializeTuple = SerializeArray;
+    type SerializeTupleStruct = SerializeArray;
+    type SerializeTupleVariant = SerializeTupleVariant;
+    type SerializeMap = SerializeMap;
+    type SerializeStruct = SerializeStruct;
+    type SerializeStructVariant = SerializeStructVariant;
+
+    fn serialize_bool(self, v: bool) -> Result<Yaml> {
+        Ok(Yaml::Boolean(v))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Yaml> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Yaml> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Yaml> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Yaml> {
+        Ok(Yaml::Integer(v))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Yaml> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Yaml> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Yaml> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Yaml> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Yaml> {
+        self.serialize_f64(v as f64)
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Yaml> {
+        Ok(Yaml::Real(float_repr(v)))
+    }
+
+    fn serialize_char(self, value: char) -> Result<Yaml> {
+        Ok(Yaml::String(value.to_string()))
+    }
+
+    fn serialize_str(self, value: &str) -> Result<Yaml> {
+        Ok(Yaml::String(value.to_owned()))
+    }
+
+    fn serialize_bytes(self, value: &[u8]) -> Result<Yaml> {
+        if self.config.bytes_repr == BytesRepr::Binary {
+            return Ok(binary_node(base64_encode(value)));
+        }
+        let vec = value.iter().map(|&b| Yaml::Integer(b as i64)).collect();
+        Ok(Yaml::Array(vec))
+    }
+
+    fn serialize_unit(self) -> Result<Yaml> {
+        Ok(Yaml::Null)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Yaml> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &str,
+        _variant_index: usize,
+        variant: &str
+    ) -> Result<Yaml> {
+        match self.config.enum_repr {
+            EnumRepr::Map => Ok(Yaml::String(variant.to_owned())),
+            EnumRepr::Tag => Ok(tagged_node(variant, Yaml::Null)),
+            EnumRepr::Untagged => Ok(Yaml::Null),
+        }
+    }
+
+    fn serialize_newtype_struct<T: ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T
+    ) -> Result<Yaml>
+        where T: ser::Serialize
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized>(
+        self,
+        _name: &str,
+        _variant_index: usize,
+        variant: &str,
+        value: &T
+    ) -> Result<Yaml>
+        where T: ser::Serialize
+    {
+        let config = self.config;
+        let payload = try!(to_yaml(value, config));
+        match config.enum_repr {
+            EnumRepr::Map => Ok(singleton_hash(try!(to_yaml(variant, config)), payload)),
+            EnumRepr::Tag => Ok(tagged_node(variant, payload)),
+            EnumRepr::Untagged => Ok(payload),
+        }
+    }
+
+    fn serialize_none(self) -> Result<Yaml> {
+        self.serialize_unit()
+    }
+
+    fn serialize_some<V: ?Sized>(self, value: &V) -> Result<Yaml>
+        where V: ser::Serialize
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SerializeArray> {
+        let array = match len {
+            None => yaml::Array::new(),
+            Some(len) => yaml::Array::with_capacity(len),
+        };
+        Ok(SerializeArray { array: array, config: self.config })
+    }
+
+    fn serialize_seq_fixed_size(self, len: usize) -> Result<SerializeArray> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SerializeArray> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize
+    ) -> Result<SerializeArray> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _enum: &'static str,
+        _idx: usize,
+        variant: &'static str,
+        len: usize
+    ) -> Result<SerializeTupleVariant> {
+        Ok(SerializeTupleVariant {
+            name: variant,
+            array: yaml::Array::with_capacity(len),
+            config: self.config,
+        })
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<SerializeMap> {
+        Ok(SerializeMap { hash: yaml::Hash::new(), next_key: None, config: self.config })
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize
+    ) -> Result<SerializeStruct> {
+        Ok(SerializeStruct { hash: yaml::Hash::new(), config: self.config })
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _enum: &'static str,
+        _idx: usize,
+        variant: &'static str,
+        _len: usize
+    ) -> Result<SerializeStructVariant> {
+        Ok(SerializeStructVariant {
+            name: variant,
+            hash: yaml::Hash::new(),
+            config: self.config,
+        })
+    }
+}
+
+pub struct SerializeArray {
+    array: yaml::Array,
+    config: TreeConfig,
+}
+
+pub struct SerializeTupleVariant {
+    name: &'static str,
+    array: yaml::Array,
+    config: TreeConfig,
+}
+
+pub struct SerializeMap {
+    hash: yaml::Hash,
+    next_key: Option<yaml::Yaml>,
+    config: TreeConfig,
+}
+
+pub struct SerializeStruct {
+    hash: yaml::Hash,
+    config: TreeConfig,
+}
+
+pub struct SerializeStructVariant {
+    name: &'static str,
+    hash: yaml::Hash,
+    config: TreeConfig,
+}
+
+impl ser::SerializeSeq for SerializeArray {
+    type Ok = yaml::Yaml;
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized>(&mut self, elem: &T) -> Result<()>
+        where T: ser::Serialize
+    {
+        self.array.push(try!(to_yaml(elem, self.config)));
+        Ok(())
+    }
+
+    fn end(self) -> Result<Yaml> {
+        Ok(Yaml::Array(self.array))
+    }
+}
+
+impl ser::SerializeTuple for SerializeArray {
+    type Ok = yaml::Yaml;
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized>(&mut self, elem: &T) -> Result<()>
+        where T: ser::Serialize
+    {
+        ser::SerializeSeq::serialize_element(self, elem)
+    }
+
+    fn end(self) -> Result<Yaml> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeTupleStruct for SerializeArray {
+    type Ok = yaml::Yaml;
+    type Error = Error;
+
+    fn serialize_field<V: ?Sized>(&mut self, value: &V) -> Result<()>
+        where V: ser::Serialize
+    {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Yaml> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeTupleVariant for SerializeTupleVariant {
+    type Ok = yaml::Yaml;
+    type Error = Error;
+
+    fn serialize_field<V: ?Sized>(&mut self, v: &V) -> Result<()>
+        where V: ser::Serialize
+    {
+        self.array.push(try!(to_yaml(v, self.config)));
+        Ok(())
+    }
+
+    fn end(self) -> Result<Yaml> {
+        let payload = Yaml::Array(self.array);
+        match self.config.enum_repr {
+            EnumRepr::Map => Ok(singleton_hash(try!(to_yaml(self.name, self.config)), payload)),
+            EnumRepr::Tag => Ok(tagged_node(self.name, payload)),
+            EnumRepr::Untagged => Ok(payload),
+        }
+    }
+}
+
+impl ser::SerializeMap for SerializeMap {
+    type Ok = yaml::Yaml;
+    type Error = Error;
+
+    fn serialize_key<T: ?Sized>(&mut self, key: &T) -> Result<()>
+        where T: ser::Serialize
+    {
+        self.next_key = Some(try!(to_yaml(key, self.config)));
+        Ok(())
+    }
+
+    fn serialize_value<T: ?Sized>(&mut self, value: &T) -> Result<()>
+        where T: ser::Serialize
+    {
+        match self.next_key.take() {
+            Some(key) => self.hash.insert(key, try!(to_yaml(value, self.config))),
+            None => panic!("serialize_value called before serialize_key"),
+        };
+        Ok(())
+    }
+
+    fn end(self) -> Result<Yaml> {
+        Ok(Yaml::Hash(self.hash))
+    }
+}
+
+impl ser::SerializeStruct for SerializeStruct {
+    type Ok = yaml::Yaml;
+    type Error = Error;
+
+    fn serialize_field<V: ?Sized>(&mut self, key: &'static str, value: &V) -> Result<()>
+        where V: ser::Serialize
+    {
+        self.hash.insert(try!(to_yaml(key, self.config)), try!(to_yaml(value, self.config)));
+        Ok(())
+    }
+
+    fn end(self) -> Result<Yaml> {
+        Ok(Yaml::Hash(self.hash))
+    }
+}
+
+impl ser::SerializeStructVariant for SerializeStructVariant {
+    type Ok = yaml::Yaml;
+    type Error = Error;
+
+    fn serialize_field<V: ?Sized>(&mut self, field: &'static str, v: &V) -> Result<()>
+        where V: ser::Serialize
+    {
+        self.hash.insert(try!(to_yaml(field, self.config)), try!(to_yaml(v, self.config)));
+        Ok(())
+    }
+
+    fn end(self) -> Result<Yaml> {
+        let payload = Yaml::Hash(self.hash);
+        match self.config.enum_repr {
+            EnumRepr::Map => Ok(singleton_hash(try!(to_yaml(self.name, self.config)), payload)),
+            EnumRepr::Tag => Ok(tagged_node(self.name, payload)),
+            EnumRepr::Untagged => Ok(payload),
+        }
+    }
+}
+
+fn singleton_hash(k: Yaml, v: Yaml) -> Yaml {
     let mut hash = yaml::Hash::new();
     hash.insert(k, v);
     Yaml::Hash(hash)
-}
\ No newline at end of file
+}
+
+/// `yaml_rust::Yaml` has no variant for a tagged node, so the public,
+/// `Yaml`-returning `Serializer` represents `EnumRepr::Tag` as a two-entry
+/// hash under these sentinel keys -- the best available encoding within
+/// `Yaml`'s own type system. This is shape-based, not type-based: a
+/// user-supplied two-entry `Hash` whose keys happen to equal
+/// `TAG_KEY`/`TAG_PAYLOAD_KEY` (both start with a NUL byte, so collision
+/// requires a deliberately crafted map) is indistinguishable from a real
+/// tagged node to anything that later shape-matches the returned `Yaml`
+/// tree. [`BINARY_KEY`] has the same limitation.
+///
+/// `to_writer_pretty`/`to_string_pretty` do not suffer from this: they
+/// build a [`Node`] tree (via [`NodeSerializer`]) instead of a `Yaml`
+/// tree, so `Emitter` recognizes tagged/binary nodes by real enum variant
+/// rather than by guessing at `Hash` shape.
+const TAG_KEY: &'static str = "\0serde_yaml::tag";
+const TAG_PAYLOAD_KEY: &'static str = "\0serde_yaml::payload";
+
+fn tagged_node(variant: &str, payload: Yaml) -> Yaml {
+    let mut hash = yaml::Hash::new();
+    hash.insert(Yaml::String(TAG_KEY.to_owned()), Yaml::String(variant.to_owned()));
+    hash.insert(Yaml::String(TAG_PAYLOAD_KEY.to_owned()), payload);
+    Yaml::Hash(hash)
+}
+
+/// Same trick as [`tagged_node`], used by the public `Serializer` to carry
+/// a `BytesRepr::Binary` buffer (already base64-encoded) through the `Yaml`
+/// tree as a one-entry sentinel hash instead of a `Yaml::String`, which
+/// would be indistinguishable from an ordinary string scalar holding the
+/// same base64 text.
+///
+/// Shape-based, with the same sentinel-collision limitation as
+/// [`TAG_KEY`]/[`TAG_PAYLOAD_KEY`]; see [`Node`] for the representation
+/// `to_writer_pretty`/`to_string_pretty` use instead.
+const BINARY_KEY: &'static str = "\0serde_yaml::binary";
+
+fn binary_node(base64: String) -> Yaml {
+    let mut hash = yaml::Hash::new();
+    hash.insert(Yaml::String(BINARY_KEY.to_owned()), Yaml::String(base64));
+    Yaml::Hash(hash)
+}
+
+/// Mirrors the subset of `yaml_rust::Yaml` that [`NodeSerializer`] produces,
+/// but with real `Tagged`/`Binary` variants instead of encoding them as
+/// sentinel-keyed hashes. `Emitter` (used by [`to_writer_pretty`]/
+/// [`to_string_pretty`]) consumes `Node` instead of `Yaml` for exactly this
+/// reason: unlike the public `Serializer`, which must fit into
+/// `yaml_rust::Yaml` and so still uses the sentinel-hash trick documented
+/// on [`tagged_node`]/[`binary_node`], `Emitter` is a type this crate fully
+/// controls, so it can tell a tagged/binary node from an ordinary map by
+/// enum variant instead of by shape -- a user-supplied map can no longer be
+/// misread as `!variant ...`/`!!binary ...` just because its keys happen to
+/// collide with a sentinel.
+enum Node {
+    Null,
+    Boolean(bool),
+    Integer(i64),
+    Real(String),
+    String(String),
+    Array(Vec<Node>),
+    Hash(Vec<(Node, Node)>),
+    Tagged(String, Box<Node>),
+    Binary(String),
+}
+
+/// Builds a complete `Node` tree for `elem`, representing enum variants
+/// and byte buffers according to `config`. Used by `to_writer_pretty`/
+/// `to_string_pretty` in place of [`to_yaml`].
+fn to_node<T>(elem: T, config: TreeConfig) -> Result<Node>
+    where T: ser::Serialize
+{
+    elem.serialize(NodeSerializer { config: config })
+}
+
+#[derive(Clone, Copy)]
+struct NodeSerializer {
+    config: TreeConfig,
+}
+
+impl ser::Serializer for NodeSerializer {
+    type Ok = Node;
+    type Error = Error;
+
+    type SerializeSeq = NodeSerializeArray;
+    type SerializeTuple = NodeSerializeArray;
+    type SerializeTupleStruct = NodeSerializeArray;
+    type SerializeTupleVariant = NodeSerializeTupleVariant;
+    type SerializeMap = NodeSerializeMap;
+    type SerializeStruct = NodeSerializeStruct;
+    type SerializeStructVariant = NodeSerializeStructVariant;
+
+    fn serialize_bool(self, v: bool) -> Result<Node> {
+        Ok(Node::Boolean(v))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Node> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Node> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Node> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Node> {
+        Ok(Node::Integer(v))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Node> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Node> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Node> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Node> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Node> {
+        self.serialize_f64(v as f64)
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Node> {
+        Ok(Node::Real(float_repr(v)))
+    }
+
+    fn serialize_char(self, value: char) -> Result<Node> {
+        Ok(Node::String(value.to_string()))
+    }
+
+    fn serialize_str(self, value: &str) -> Result<Node> {
+        Ok(Node::String(value.to_owned()))
+    }
+
+    fn serialize_bytes(self, value: &[u8]) -> Result<Node> {
+        if self.config.bytes_repr == BytesRepr::Binary {
+            return Ok(Node::Binary(base64_encode(value)));
+        }
+        let vec = value.iter().map(|&b| Node::Integer(b as i64)).collect();
+        Ok(Node::Array(vec))
+    }
+
+    fn serialize_unit(self) -> Result<Node> {
+        Ok(Node::Null)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Node> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &str,
+        _variant_index: usize,
+        variant: &str
+    ) -> Result<Node> {
+        match self.config.enum_repr {
+            EnumRepr::Map => Ok(Node::String(variant.to_owned())),
+            EnumRepr::Tag => Ok(Node::Tagged(variant.to_owned(), Box::new(Node::Null))),
+            EnumRepr::Untagged => Ok(Node::Null),
+        }
+    }
+
+    fn serialize_newtype_struct<T: ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T
+    ) -> Result<Node>
+        where T: ser::Serialize
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized>(
+        self,
+        _name: &str,
+        _variant_index: usize,
+        variant: &str,
+        value: &T
+    ) -> Result<Node>
+        where T: ser::Serialize
+    {
+        let config = self.config;
+        let payload = try!(to_node(value, config));
+        match config.enum_repr {
+            EnumRepr::Map => Ok(Node::Hash(vec![(Node::String(variant.to_owned()), payload)])),
+            EnumRepr::Tag => Ok(Node::Tagged(variant.to_owned(), Box::new(payload))),
+            EnumRepr::Untagged => Ok(payload),
+        }
+    }
+
+    fn serialize_none(self) -> Result<Node> {
+        self.serialize_unit()
+    }
+
+    fn serialize_some<V: ?Sized>(self, value: &V) -> Result<Node>
+        where V: ser::Serialize
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<NodeSerializeArray> {
+        let array = match len {
+            None => Vec::new(),
+            Some(len) => Vec::with_capacity(len),
+        };
+        Ok(NodeSerializeArray { array: array, config: self.config })
+    }
+
+    fn serialize_seq_fixed_size(self, len: usize) -> Result<NodeSerializeArray> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<NodeSerializeArray> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize
+    ) -> Result<NodeSerializeArray> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _enum: &'static str,
+        _idx: usize,
+        variant: &'static str,
+        len: usize
+    ) -> Result<NodeSerializeTupleVariant> {
+        Ok(NodeSerializeTupleVariant {
+            name: variant,
+            array: Vec::with_capacity(len),
+            config: self.config,
+        })
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<NodeSerializeMap> {
+        Ok(NodeSerializeMap { entries: Vec::new(), next_key: None, config: self.config })
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize
+    ) -> Result<NodeSerializeStruct> {
+        Ok(NodeSerializeStruct { entries: Vec::new(), config: self.config })
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _enum: &'static str,
+        _idx: usize,
+        variant: &'static str,
+        _len: usize
+    ) -> Result<NodeSerializeStructVariant> {
+        Ok(NodeSerializeStructVariant {
+            name: variant,
+            entries: Vec::new(),
+            config: self.config,
+        })
+    }
+}
+
+struct NodeSerializeArray {
+    array: Vec<Node>,
+    config: TreeConfig,
+}
+
+struct NodeSerializeTupleVariant {
+    name: &'static str,
+    array: Vec<Node>,
+    config: TreeConfig,
+}
+
+struct NodeSerializeMap {
+    entries: Vec<(Node, Node)>,
+    next_key: Option<Node>,
+    config: TreeConfig,
+}
+
+struct NodeSerializeStruct {
+    entries: Vec<(Node, Node)>,
+    config: TreeConfig,
+}
+
+struct NodeSerializeStructVariant {
+    name: &'static str,
+    entries: Vec<(Node, Node)>,
+    config: TreeConfig,
+}
+
+impl ser::SerializeSeq for NodeSerializeArray {
+    type Ok = Node;
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized>(&mut self, elem: &T) -> Result<()>
+        where T: ser::Serialize
+    {
+        self.array.push(try!(to_node(elem, self.config)));
+        Ok(())
+    }
+
+    fn end(self) -> Result<Node> {
+        Ok(Node::Array(self.array))
+    }
+}
+
+impl ser::SerializeTuple for NodeSerializeArray {
+    type Ok = Node;
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized>(&mut self, elem: &T) -> Result<()>
+        where T: ser::Serialize
+    {
+        ser::SerializeSeq::serialize_element(self, elem)
+    }
+
+    fn end(self) -> Result<Node> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeTupleStruct for NodeSerializeArray {
+    type Ok = Node;
+    type Error = Error;
+
+    fn serialize_field<V: ?Sized>(&mut self, value: &V) -> Result<()>
+        where V: ser::Serialize
+    {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Node> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeTupleVariant for NodeSerializeTupleVariant {
+    type Ok = Node;
+    type Error = Error;
+
+    fn serialize_field<V: ?Sized>(&mut self, v: &V) -> Result<()>
+        where V: ser::Serialize
+    {
+        self.array.push(try!(to_node(v, self.config)));
+        Ok(())
+    }
+
+    fn end(self) -> Result<Node> {
+        let payload = Node::Array(self.array);
+        match self.config.enum_repr {
+            EnumRepr::Map => {
+                Ok(Node::Hash(vec![(Node::String(self.name.to_owned()), payload)]))
+            }
+            EnumRepr::Tag => Ok(Node::Tagged(self.name.to_owned(), Box::new(payload))),
+            EnumRepr::Untagged => Ok(payload),
+        }
+    }
+}
+
+impl ser::SerializeMap for NodeSerializeMap {
+    type Ok = Node;
+    type Error = Error;
+
+    fn serialize_key<T: ?Sized>(&mut self, key: &T) -> Result<()>
+        where T: ser::Serialize
+    {
+        self.next_key = Some(try!(to_node(key, self.config)));
+        Ok(())
+    }
+
+    fn serialize_value<T: ?Sized>(&mut self, value: &T) -> Result<()>
+        where T: ser::Serialize
+    {
+        match self.next_key.take() {
+            Some(key) => self.entries.push((key, try!(to_node(value, self.config)))),
+            None => panic!("serialize_value called before serialize_key"),
+        };
+        Ok(())
+    }
+
+    fn end(self) -> Result<Node> {
+        Ok(Node::Hash(self.entries))
+    }
+}
+
+impl ser::SerializeStruct for NodeSerializeStruct {
+    type Ok = Node;
+    type Error = Error;
+
+    fn serialize_field<V: ?Sized>(&mut self, key: &'static str, value: &V) -> Result<()>
+        where V: ser::Serialize
+    {
+        self.entries.push((Node::String(key.to_owned()), try!(to_node(value, self.config))));
+        Ok(())
+    }
+
+    fn end(self) -> Result<Node> {
+        Ok(Node::Hash(self.entries))
+    }
+}
+
+impl ser::SerializeStructVariant for NodeSerializeStructVariant {
+    type Ok = Node;
+    type Error = Error;
+
+    fn serialize_field<V: ?Sized>(&mut self, field: &'static str, v: &V) -> Result<()>
+        where V: ser::Serialize
+    {
+        self.entries.push((Node::String(field.to_owned()), try!(to_node(v, self.config))));
+        Ok(())
+    }
+
+    fn end(self) -> Result<Node> {
+        let payload = Node::Hash(self.entries);
+        match self.config.enum_repr {
+            EnumRepr::Map => {
+                Ok(Node::Hash(vec![(Node::String(self.name.to_owned()), payload)]))
+            }
+            EnumRepr::Tag => Ok(Node::Tagged(self.name.to_owned(), Box::new(payload))),
+            EnumRepr::Untagged => Ok(payload),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use yaml_rust::YamlLoader;
+
+    fn parse(s: &str) -> Yaml {
+        YamlLoader::load_from_str(s).unwrap().remove(0)
+    }
+
+    struct Inner {
+        a: i64,
+    }
+
+    impl ser::Serialize for Inner {
+        fn serialize<S>(&self, serializer: S) -> ::std::result::Result<S::Ok, S::Error>
+            where S: ser::Serializer
+        {
+            use serde::ser::SerializeStruct;
+            let mut s = try!(serializer.serialize_struct("Inner", 1));
+            try!(s.serialize_field("a", &self.a));
+            s.end()
+        }
+    }
+
+    struct Outer {
+        nested: Inner,
+        sibling: i64,
+    }
+
+    impl ser::Serialize for Outer {
+        fn serialize<S>(&self, serializer: S) -> ::std::result::Result<S::Ok, S::Error>
+            where S: ser::Serializer
+        {
+            use serde::ser::SerializeStruct;
+            let mut s = try!(serializer.serialize_struct("Outer", 2));
+            try!(s.serialize_field("nested", &self.nested));
+            try!(s.serialize_field("sibling", &self.sibling));
+            s.end()
+        }
+    }
+
+    struct WithItems {
+        items: Vec<i64>,
+    }
+
+    impl ser::Serialize for WithItems {
+        fn serialize<S>(&self, serializer: S) -> ::std::result::Result<S::Ok, S::Error>
+            where S: ser::Serializer
+        {
+            use serde::ser::SerializeStruct;
+            let mut s = try!(serializer.serialize_struct("WithItems", 1));
+            try!(s.serialize_field("items", &self.items));
+            s.end()
+        }
+    }
+
+    struct TaggedPayload {
+        x: i64,
+        y: i64,
+    }
+
+    impl ser::Serialize for TaggedPayload {
+        fn serialize<S>(&self, serializer: S) -> ::std::result::Result<S::Ok, S::Error>
+            where S: ser::Serializer
+        {
+            use serde::ser::SerializeStructVariant;
+            let mut sv = try!(serializer.serialize_struct_variant("TaggedPayload", 0, "Variant", 2));
+            try!(sv.serialize_field("x", &self.x));
+            try!(sv.serialize_field("y", &self.y));
+            sv.end()
+        }
+    }
+
+    #[test]
+    fn serializer_builds_a_yaml_tree_directly() {
+        // `Serializer` is the public tree-building entry point: driving a
+        // value through it (rather than `to_writer`/`to_string_pretty`)
+        // yields a `yaml_rust::Yaml` directly, with no document wrapping.
+        use serde::ser::Serialize;
+
+        let value = Outer { nested: Inner { a: 1 }, sibling: 5 };
+        let doc = value.serialize(Serializer::new()).unwrap();
+        assert_eq!(doc["nested"]["a"].as_i64(), Some(1));
+        assert_eq!(doc["sibling"].as_i64(), Some(5));
+    }
+
+    #[test]
+    fn pretty_default_config_indents_nested_mapping() {
+        let value = Outer { nested: Inner { a: 1 }, sibling: 5 };
+        let out = to_string_pretty(&value, EmitterConfig::default()).unwrap();
+        let doc = parse(&out);
+        assert_eq!(doc["nested"]["a"].as_i64(), Some(1));
+        assert_eq!(doc["sibling"].as_i64(), Some(5));
+    }
+
+    #[test]
+    fn pretty_compact_keeps_nested_sequence_at_the_parent_indent() {
+        // `compact: true` (the default) keeps a nested block sequence at
+        // the same indent as its parent key, since the `-` marker already
+        // disambiguates it from the key's own line.
+        let value = WithItems { items: vec![1, 2] };
+        let out = to_string_pretty(&value, EmitterConfig::default()).unwrap();
+        assert_eq!(out, "---\nitems:\n- 1\n- 2");
+    }
+
+    #[test]
+    fn pretty_non_compact_indents_nested_sequence_one_level_deeper() {
+        // With `compact: false`, a nested sequence indents one level
+        // deeper than its parent key instead of lining up with it.
+        let config = EmitterConfig { compact: false, ..EmitterConfig::default() };
+        let value = WithItems { items: vec![1, 2] };
+        let out = to_string_pretty(&value, config).unwrap();
+        assert_eq!(out, "---\nitems:\n  - 1\n  - 2");
+    }
+
+    #[test]
+    fn pretty_best_indent_controls_nested_mapping_indentation() {
+        let config = EmitterConfig { best_indent: 4, ..EmitterConfig::default() };
+        let value = Outer { nested: Inner { a: 1 }, sibling: 5 };
+        let out = to_string_pretty(&value, config).unwrap();
+        assert_eq!(out, "---\nnested:\n    a: 1\nsibling: 5");
+    }
+
+    #[test]
+    fn pretty_color_wraps_output_in_ansi_escapes() {
+        let config = EmitterConfig { color: true, ..EmitterConfig::default() };
+        let value = Inner { a: 1 };
+        let out = to_string_pretty(&value, config).unwrap();
+        assert_eq!(out, "---\n\x1b[36ma\x1b[0m\x1b[2m:\x1b[0m \x1b[33m1\x1b[0m");
+    }
+
+    #[test]
+    fn enum_repr_map_round_trips_struct_variant_payload() {
+        let value = TaggedPayload { x: 1, y: 2 };
+        let out = to_string_with_enum_repr(&value, EnumRepr::Map).unwrap();
+        let doc = parse(&out);
+        assert_eq!(doc["Variant"]["x"].as_i64(), Some(1));
+        assert_eq!(doc["Variant"]["y"].as_i64(), Some(2));
+    }
+
+    #[test]
+    fn enum_repr_tag_indents_payload_under_the_tag() {
+        let value = TaggedPayload { x: 1, y: 2 };
+        let out = to_string_with_enum_repr(&value, EnumRepr::Tag).unwrap();
+        let mut lines = out.lines();
+        assert_eq!(lines.next(), Some("---"));
+        assert_eq!(lines.next(), Some("!Variant"));
+        for payload_line in lines {
+            assert!(payload_line.starts_with("  "),
+                    "tagged payload line {:?} is not indented under its tag", payload_line);
+        }
+    }
+
+    #[test]
+    fn pretty_does_not_mistake_a_user_map_for_a_tagged_or_binary_node() {
+        // A plain map whose keys happen to collide with the sentinel keys
+        // `tagged_node`/`binary_node` use internally (see `Node`'s doc
+        // comment) must still round-trip as an ordinary mapping through
+        // `to_string_pretty`, not get reinterpreted as `!variant ...` or
+        // `!!binary ...`.
+        use std::collections::BTreeMap;
+
+        let mut tag_like = BTreeMap::new();
+        tag_like.insert("\0serde_yaml::tag".to_owned(), "not a variant".to_owned());
+        tag_like.insert("\0serde_yaml::payload".to_owned(), "not a payload".to_owned());
+        let out = to_string_pretty(&tag_like, EmitterConfig::default()).unwrap();
+        let doc = parse(&out);
+        assert_eq!(doc["\0serde_yaml::tag"].as_str(), Some("not a variant"));
+        assert_eq!(doc["\0serde_yaml::payload"].as_str(), Some("not a payload"));
+
+        let mut binary_like = BTreeMap::new();
+        binary_like.insert("\0serde_yaml::binary".to_owned(), "not base64".to_owned());
+        let out = to_string_pretty(&binary_like, EmitterConfig::default()).unwrap();
+        let doc = parse(&out);
+        assert_eq!(doc["\0serde_yaml::binary"].as_str(), Some("not base64"));
+    }
+
+    #[test]
+    fn enum_repr_composes_with_bytes_repr_in_the_streaming_path() {
+        // `to_writer_with_config`/`to_string_with_config` let a caller
+        // combine `bytes_repr` and `enum_repr` in a single streaming pass,
+        // instead of being forced to pick one of the single-knob helpers
+        // (or drop into the tree-building path) to get both.
+        struct Wrapper<'a>(&'a [u8]);
+
+        impl<'a> ser::Serialize for Wrapper<'a> {
+            fn serialize<S>(&self, serializer: S) -> ::std::result::Result<S::Ok, S::Error>
+                where S: ser::Serializer
+            {
+                use serde::ser::SerializeStructVariant;
+                let mut sv = try!(serializer.serialize_struct_variant("Wrapper", 0, "Chunk", 1));
+                try!(sv.serialize_field("data", &Bytes(self.0)));
+                sv.end()
+            }
+        }
+
+        let bytes = [0x01u8, 0x02, 0xff];
+        let out = to_string_with_config(&Wrapper(&bytes), BytesRepr::Binary, EnumRepr::Tag).unwrap();
+        assert_eq!(out, format!("---\n!Chunk\n  data: !!binary {}", base64_encode(&bytes)));
+    }
+
+    #[test]
+    fn float_repr_matches_yaml_core_schema() {
+        let out = to_string(&vec![1.5f64,
+                                   1.0f64,
+                                   ::std::f64::NAN,
+                                   ::std::f64::INFINITY,
+                                   ::std::f64::NEG_INFINITY])
+            .unwrap();
+        assert_eq!(out, "---\n- 1.5\n- 1.0\n- .nan\n- .inf\n- -.inf");
+    }
+
+    struct Bytes<'a>(&'a [u8]);
+
+    impl<'a> ser::Serialize for Bytes<'a> {
+        fn serialize<S>(&self, serializer: S) -> ::std::result::Result<S::Ok, S::Error>
+            where S: ser::Serializer
+        {
+            serializer.serialize_bytes(self.0)
+        }
+    }
+
+    #[test]
+    fn binary_bytes_serialize_as_base64() {
+        let bytes = [0x01u8, 0x02, 0x03, 0xff];
+        let out = to_string_with_bytes_repr(&Bytes(&bytes), BytesRepr::Binary).unwrap();
+        assert_eq!(out, format!("---\n!!binary {}", base64_encode(&bytes)));
+    }
+
+    #[test]
+    fn plain_vec_u8_ignores_bytes_repr() {
+        // `Vec<u8>`'s blanket `Serialize` impl drives `serialize_seq`/
+        // `serialize_u8` per element rather than `serialize_bytes`, so it
+        // never picks up `BytesRepr::Binary`; only a type that calls
+        // `serialize_bytes` directly (e.g. `serde_bytes::Bytes`, or the
+        // `Bytes` wrapper above) does.
+        let bytes = vec![0x01u8, 0x02, 0x03, 0xff];
+        let out = to_string_with_bytes_repr(&bytes, BytesRepr::Binary).unwrap();
+        assert_eq!(out, "---\n- 1\n- 2\n- 3\n- 255");
+    }
+
+    struct TaggedScalar(i64);
+
+    impl ser::Serialize for TaggedScalar {
+        fn serialize<S>(&self, serializer: S) -> ::std::result::Result<S::Ok, S::Error>
+            where S: ser::Serializer
+        {
+            serializer.serialize_newtype_variant("TaggedScalar", 0, "Variant", &self.0)
+        }
+    }
+
+    #[test]
+    fn to_string_pretty_never_ends_with_trailing_newline() {
+        // Exact-string check: before this fix, `emit_collection_value`'s
+        // inline-scalar arm terminated every entry (including the last)
+        // with a `\n`, while `emit_tagged`'s structurally identical arm
+        // never did -- so `to_string_pretty` ended with a trailing `\n`
+        // when the root was a block collection but not when it was a
+        // scalar-payload tagged node. Neither should, to match `to_string`.
+        let mapping = Outer { nested: Inner { a: 1 }, sibling: 5 };
+        let out = to_string_pretty(&mapping, EmitterConfig::default()).unwrap();
+        assert_eq!(out, "---\nnested:\n  a: 1\nsibling: 5");
+
+        let config = EmitterConfig { enum_repr: EnumRepr::Tag, ..EmitterConfig::default() };
+        let out = to_string_pretty(&TaggedScalar(7), config).unwrap();
+        assert_eq!(out, "---\n!Variant 7");
+    }
+
+    #[test]
+    fn pretty_config_honors_bytes_repr() {
+        // Before this fix, the tree-building path then behind
+        // `to_writer_pretty` had no `bytes_repr` field at all, so
+        // `!!binary` output was only reachable through the plain streaming
+        // `to_writer_with_bytes_repr` — unavailable the moment a caller
+        // also wanted pretty layout.
+        let bytes = [0x01u8, 0x02, 0x03, 0xff];
+        let config = EmitterConfig { bytes_repr: BytesRepr::Binary, ..EmitterConfig::default() };
+        let out = to_string_pretty(&Bytes(&bytes), config).unwrap();
+        assert_eq!(out, format!("---\n!!binary {}", base64_encode(&bytes)));
+    }
+
+    #[test]
+    fn to_string_has_document_start_marker_but_no_extra_indent() {
+        // `to_writer`/`to_string` restore the `---` document-start marker
+        // that `yaml_rust::YamlEmitter` always wrote, but the root document
+        // itself still starts directly at depth 0 with no spurious leading
+        // blank line or indent.
+        let value = Outer { nested: Inner { a: 1 }, sibling: 5 };
+        let out = to_string(&value).unwrap();
+        assert_eq!(out, "---\nnested:\n  a: 1\nsibling: 5");
+    }
+}